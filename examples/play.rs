@@ -60,12 +60,12 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let connection = midi_output.connect(&midi_ports[port_number], "lyrica-play")?;
     let file = fs::read(filename)?;
-    let midi_file = MidiFile::from_bytes(&file);
+    let midi_file = MidiFile::from_bytes(&file)?;
     let mut player = MidiPlayer::new(connection);
-    player.set_midi_file(midi_file);
+    player.set_midi_file(midi_file)?;
 
     while !player.is_finished() {
-        player.update();
+        player.update()?;
         // TODO: Not entirely sure why this is needed, but without this, playback freezes
         // after the first note. Might be because `update` is executed so fast that
         // my slipshod code can't handle it.
@@ -24,15 +24,92 @@
 
 use midly::{
     live::LiveEvent,
-    num::{u24, u28, u4, u7},
-    MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
+    num::{u15, u24, u28, u4, u7},
+    Format, Header, MetaMessage, MidiMessage, Smf, Timing, TrackEvent, TrackEventKind,
 };
-use std::{collections::VecDeque, time::Instant};
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+pub use midir::{MidiInput, MidiInputConnection, MidiInputPort, MidiOutput, MidiOutputConnection, MidiOutputPort};
 
-pub use midir::{MidiOutput, MidiOutputConnection, MidiOutputPort};
+/// A software-synth [`MidiSink`], for embedding a self-contained
+/// MIDI-to-audio player with no OS/external synth dependency. Requires the
+/// `synth` feature.
+#[cfg(feature = "synth")]
+pub mod synth;
 
 const ALL_SOUND_OFF_CC: u7 = u7::new(123);
 
+/// What can go wrong loading and playing a [`MidiFile`]: malformed input that
+/// fails to parse, or a [`MidiSink`] rejecting a message, e.g. because the
+/// output device was unplugged mid-playback. [`MidiRecorder`] reports its own
+/// errors through `midir` and `std::io` types instead.
+#[derive(Debug)]
+pub enum MidiError {
+    /// `Smf::parse` couldn't make sense of the input.
+    Parse(midly::Error),
+    /// A [`MidiSink`] failed to send a message.
+    Send(midir::SendError),
+}
+
+impl std::fmt::Display for MidiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(err) => write!(f, "failed to parse MIDI data: {err}"),
+            Self::Send(err) => write!(f, "failed to send a MIDI event: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for MidiError {}
+
+/// Somewhere raw MIDI bytes can be sent to make sound: an OS/external synth
+/// reached through [`MidiOutputConnection`], or an in-crate software synth
+/// such as [`synth::SynthSink`]. `MidiFile` and `MidiPlayer` are generic over
+/// this so the same tick-advancing logic drives either one.
+pub trait MidiSink {
+    /// Sends a single already-encoded MIDI message, e.g. a Note On or a
+    /// complete (possibly reassembled) SysEx, including any leading status
+    /// byte.
+    fn send(&mut self, bytes: &[u8]) -> Result<(), MidiError>;
+
+    /// Silences every channel, e.g. before pausing or seeking so notes don't
+    /// hang. The default implementation sends an [All Sound
+    /// Off](http://midi.teragonaudio.com/tech/midispec/ntnoff.htm) Control
+    /// Change to each of the 16 channels.
+    fn all_sound_off(&mut self) -> Result<(), MidiError> {
+        let mut event_bytes = Vec::new();
+
+        for i in 0..16 {
+            let event = LiveEvent::Midi {
+                channel: u4::new(i),
+                message: MidiMessage::Controller {
+                    controller: ALL_SOUND_OFF_CC,
+                    value: u7::new(0),
+                },
+            };
+
+            event
+                .write_std(&mut event_bytes)
+                .expect("writing to an in-memory buffer should never fail");
+            self.send(&event_bytes)?;
+            event_bytes.clear();
+        }
+
+        Ok(())
+    }
+}
+
+impl MidiSink for MidiOutputConnection {
+    fn send(&mut self, bytes: &[u8]) -> Result<(), MidiError> {
+        MidiOutputConnection::send(self, bytes).map_err(MidiError::Send)
+    }
+}
+
 enum MidiFileFormat {
     Sequential { current: usize },
     Parallel,
@@ -53,16 +130,71 @@ impl From<midly::Format> for MidiFileFormat {
 enum OwnedTrackEventKind {
     ToSynth(Vec<u8>),
     Tempo(u24),
+    Meta(MetaEvent),
     InessentialMeta,
 }
 
+/// A decoded meta event worth surfacing to a caller (e.g. for a GUI or
+/// karaoke front-end to display song structure and lyrics in sync with
+/// playback). Every other [`MetaMessage`] except `Tempo` is collapsed into
+/// [`OwnedTrackEventKind::InessentialMeta`] and dropped.
+#[derive(Clone, Debug)]
+pub enum MetaEvent {
+    TrackName(String),
+    InstrumentName(String),
+    Marker(String),
+    CuePoint(String),
+    Lyric(String),
+    TimeSignature {
+        numerator: u8,
+        denominator: u32,
+        clocks_per_click: u8,
+        notated_32nds_per_quarter_note: u8,
+    },
+    KeySignature {
+        sharps_or_flats: i8,
+        is_minor: bool,
+        /// A human-readable name derived from `sharps_or_flats`/`is_minor`,
+        /// e.g. "F# minor" or "Bb major".
+        name: String,
+    },
+    EndOfTrack,
+}
+
+/// Maps a key signature's sharps/flats count (negative for flats) and
+/// major/minor flag to a human-readable name, e.g. `(3, true)` -> `"F#
+/// minor"`, `(-2, false)` -> `"Bb major"`.
+fn key_signature_name(sharps_or_flats: i8, is_minor: bool) -> String {
+    const MAJOR_NAMES: [&str; 15] = [
+        "Cb", "Gb", "Db", "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#",
+    ];
+    const MINOR_NAMES: [&str; 15] = [
+        "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#", "G#", "D#", "A#",
+    ];
+
+    let index = (sharps_or_flats.clamp(-7, 7) + 7) as usize;
+    let tonic = if is_minor {
+        MINOR_NAMES[index]
+    } else {
+        MAJOR_NAMES[index]
+    };
+
+    format!("{tonic} {}", if is_minor { "minor" } else { "major" })
+}
+
+fn decode_meta_text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
 impl<'file> From<TrackEventKind<'file>> for OwnedTrackEventKind {
     fn from(event: TrackEventKind<'file>) -> Self {
         match event {
             TrackEventKind::Midi { channel, message } => {
                 let event = LiveEvent::Midi { channel, message };
                 let mut event_bytes = Vec::new();
-                event.write_std(&mut event_bytes).unwrap();
+                event
+                    .write_std(&mut event_bytes)
+                    .expect("writing to an in-memory buffer should never fail");
                 Self::ToSynth(event_bytes)
             }
 
@@ -76,10 +208,57 @@ impl<'file> From<TrackEventKind<'file>> for OwnedTrackEventKind {
                 Self::ToSynth(event_bytes)
             }
 
-            TrackEventKind::Escape(_) => todo!("MIDI escape events are unimplemented"),
+            // Unlike `SysEx`, an escape event's payload is already the
+            // exact bytes to transmit (e.g. a continued SysEx fragment or
+            // miscellaneous real-time bytes), with no leading status byte
+            // to restore.
+            TrackEventKind::Escape(bytes) => Self::ToSynth(bytes.to_vec()),
 
             TrackEventKind::Meta(event) => match event {
                 MetaMessage::Tempo(tempo) => Self::Tempo(tempo),
+
+                MetaMessage::TrackName(name) => {
+                    Self::Meta(MetaEvent::TrackName(decode_meta_text(name)))
+                }
+
+                MetaMessage::InstrumentName(name) => {
+                    Self::Meta(MetaEvent::InstrumentName(decode_meta_text(name)))
+                }
+
+                MetaMessage::Marker(name) => Self::Meta(MetaEvent::Marker(decode_meta_text(name))),
+
+                MetaMessage::CuePoint(name) => {
+                    Self::Meta(MetaEvent::CuePoint(decode_meta_text(name)))
+                }
+
+                MetaMessage::Lyric(text) => Self::Meta(MetaEvent::Lyric(decode_meta_text(text))),
+
+                MetaMessage::TimeSignature(
+                    numerator,
+                    denominator_pow2,
+                    clocks_per_click,
+                    notated_32nds_per_quarter_note,
+                ) => Self::Meta(MetaEvent::TimeSignature {
+                    numerator,
+                    // `denominator_pow2` is a raw byte from the file and
+                    // isn't bounds-checked by the parser, so a malformed SMF
+                    // can claim a shift of 32 or more; saturate instead of
+                    // overflowing the shift.
+                    denominator: 1u32.checked_shl(denominator_pow2.into()).unwrap_or(u32::MAX),
+                    clocks_per_click,
+                    notated_32nds_per_quarter_note,
+                }),
+
+                MetaMessage::KeySignature(sharps_or_flats, is_minor) => {
+                    Self::Meta(MetaEvent::KeySignature {
+                        sharps_or_flats,
+                        is_minor,
+                        name: key_signature_name(sharps_or_flats, is_minor),
+                    })
+                }
+
+                MetaMessage::EndOfTrack => Self::Meta(MetaEvent::EndOfTrack),
+
                 _ => Self::InessentialMeta,
             },
         }
@@ -106,23 +285,76 @@ struct TrackProgress {
     next_event: usize,
 }
 
-/// Sends an [All Sound Off](http://midi.teragonaudio.com/tech/midispec/ntnoff.htm)
-/// message to all channels.
-fn all_sound_off(connection: &mut MidiOutputConnection) {
-    let mut event_bytes = Vec::new();
-
-    for i in 0..16 {
-        let event = LiveEvent::Midi {
-            channel: u4::new(i),
-            message: MidiMessage::Controller {
-                controller: ALL_SOUND_OFF_CC,
-                value: u7::new(0),
-            },
+/// Per-channel controller/program/pitch-bend/pressure state accumulated
+/// while chasing a seek, so it can be replayed on the new connection instead
+/// of leaving the synth stuck with whatever it had before the jump.
+#[derive(Clone, Default)]
+struct ChannelChaseState {
+    program_change: Option<u7>,
+    // Controller number -> value, kept in the order each number was last
+    // touched so RPN/NRPN parameter-select sequences replay coherently.
+    controllers: Vec<(u7, u7)>,
+    pitch_bend: Option<midly::PitchBend>,
+    channel_pressure: Option<u7>,
+}
+
+impl ChannelChaseState {
+    fn record(&mut self, message: MidiMessage) {
+        match message {
+            MidiMessage::ProgramChange { program } => self.program_change = Some(program),
+
+            MidiMessage::Controller { controller, value } => {
+                self.controllers.retain(|(existing, _)| *existing != controller);
+                self.controllers.push((controller, value));
+            }
+
+            MidiMessage::PitchBend { bend } => self.pitch_bend = Some(bend),
+            MidiMessage::ChannelAftertouch { vel } => self.channel_pressure = Some(vel),
+
+            // Deliberately not chased: Note On/Off would sound phantom notes,
+            // and per-key Aftertouch has no "current state" worth restoring.
+            MidiMessage::NoteOff { .. }
+            | MidiMessage::NoteOn { .. }
+            | MidiMessage::Aftertouch { .. } => {}
+        }
+    }
+
+    /// Sends the chased state to `sink`, in the order it was recorded.
+    fn replay(&self, channel: u4, sink: &mut impl MidiSink) -> Result<(), MidiError> {
+        let mut event_bytes = Vec::new();
+        let mut send = |message: MidiMessage, event_bytes: &mut Vec<u8>| -> Result<(), MidiError> {
+            let event = LiveEvent::Midi { channel, message };
+            event
+                .write_std(&mut *event_bytes)
+                .expect("writing to an in-memory buffer should never fail");
+            sink.send(event_bytes)?;
+            event_bytes.clear();
+            Ok(())
         };
 
-        event.write_std(&mut event_bytes).unwrap();
-        connection.send(&event_bytes).unwrap();
-        event_bytes.clear();
+        if let Some(program) = self.program_change {
+            send(MidiMessage::ProgramChange { program }, &mut event_bytes)?;
+        }
+
+        for (controller, value) in &self.controllers {
+            send(
+                MidiMessage::Controller {
+                    controller: *controller,
+                    value: *value,
+                },
+                &mut event_bytes,
+            )?;
+        }
+
+        if let Some(bend) = self.pitch_bend {
+            send(MidiMessage::PitchBend { bend }, &mut event_bytes)?;
+        }
+
+        if let Some(vel) = self.channel_pressure {
+            send(MidiMessage::ChannelAftertouch { vel }, &mut event_bytes)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -130,22 +362,48 @@ pub struct MidiFile {
     ticks_per_beat: u16,
     // borrowed for life from `nodi`
     microseconds_per_tick: f64,
+    // `false` for SMPTE/timecode-timed files, where ticks are fixed-rate
+    // wall-clock subframes rather than beat subdivisions, so `Tempo` meta
+    // events must not be allowed to overwrite `microseconds_per_tick`.
+    metrical_timing: bool,
     timer: f64,
+    // Position in the song's own timeline, in seconds, independent of
+    // `playback_speed`: it advances one tick's worth of song-time
+    // (`microseconds_per_tick`) per tick regardless of how fast wall-clock
+    // time is mapped onto ticks. Reported to `on_meta` callbacks alongside
+    // each event so callers can sync a lyric/marker display to the song.
+    elapsed_seconds: f64,
     loop_point: Option<f64>,
     format: MidiFileFormat,
     tracks: Vec<VecDeque<OwnedTrackEvent>>,
     progress: Vec<TrackProgress>,
     paused: bool,
+    // 1.0 = normal speed, 2.0 = double, 0.5 = half. Scales the tick clock
+    // directly rather than the authored tempo, so it composes cleanly with
+    // `Tempo` meta events and survives tempo changes mid-file.
+    playback_speed: f64,
 }
 
 impl MidiFile {
-    pub fn from_bytes(bytes: &[u8]) -> Self {
-        let parsed_file = Smf::parse(bytes).unwrap();
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MidiError> {
+        let parsed_file = Smf::parse(bytes).map_err(MidiError::Parse)?;
 
-        let ticks_per_beat = match parsed_file.header.timing {
-            Timing::Metrical(ticks_per_beat) => ticks_per_beat.into(),
-            Timing::Timecode(_, _) => todo!("timecode timing is unimplemented"),
-        };
+        let (ticks_per_beat, microseconds_per_tick, metrical_timing) =
+            match parsed_file.header.timing {
+                Timing::Metrical(ticks_per_beat) => (ticks_per_beat.into(), 0.0, true),
+
+                // Timecode timing fixes an absolute tick duration up front:
+                // there's no tempo to wait for, since ticks are subframes of
+                // a real-time frame rate (e.g. 30 fps, 29.97 fps, ...) rather
+                // than subdivisions of a beat. `Fps::as_f32` already decodes
+                // the drop-frame 29.97 fps case (encoded in the header as the
+                // two's complement of 29), so no special-casing is needed here.
+                Timing::Timecode(fps, subframes_per_frame) => (
+                    0,
+                    1_000_000.0 / (fps.as_f32() as f64 * subframes_per_frame as f64),
+                    false,
+                ),
+            };
 
         // This looks like this performs far too many allocations, but
         // in the optimal case, the parsing library would make most of
@@ -161,24 +419,43 @@ impl MidiFile {
 
         let progress = vec![Default::default(); tracks.len()];
 
-        Self {
+        Ok(Self {
             ticks_per_beat,
-            microseconds_per_tick: 0.0,
+            microseconds_per_tick,
+            metrical_timing,
             timer: 0.0,
+            elapsed_seconds: 0.0,
             loop_point: None,
             format: parsed_file.header.format.into(),
             tracks,
             progress,
             paused: false,
-        }
+            playback_speed: 1.0,
+        })
     }
 
-    pub fn set_paused(&mut self, paused: bool, connection: &mut MidiOutputConnection) {
+    pub fn set_paused(&mut self, paused: bool, sink: &mut impl MidiSink) -> Result<(), MidiError> {
         self.paused = paused;
 
         if paused {
-            all_sound_off(connection);
+            sink.all_sound_off()?;
         }
+
+        Ok(())
+    }
+
+    /// Sets the playback rate (1.0 = normal, 2.0 = double speed, 0.5 = half),
+    /// independent of the file's authored tempo. Unlike retiming MIDI pitch,
+    /// this doesn't affect pitch. Also known as tempo scaling: it divides
+    /// the tick threshold `update` waits on, rather than touching
+    /// `microseconds_per_tick` itself, so it composes cleanly with `Tempo`
+    /// meta events reached mid-playback.
+    pub fn set_playback_speed(&mut self, playback_speed: f64) {
+        self.playback_speed = playback_speed;
+    }
+
+    pub fn playback_speed(&self) -> f64 {
+        self.playback_speed
     }
 
     // TODO: is passing `None` here useful?
@@ -208,10 +485,21 @@ impl MidiFile {
     }
 
     /// Seek to the given time in seconds.
-    pub fn seek_to(&mut self, seconds: f64, connection: &mut MidiOutputConnection) {
-        all_sound_off(connection);
+    ///
+    /// This "chases" the state that accumulated before the target: the
+    /// latest Program Change, Controller, Pitch Bend, and Channel Pressure
+    /// per channel, plus the latest Tempo, are replayed so the synth doesn't
+    /// keep playing the wrong instrument or tempo after the jump. Note
+    /// On/Off is deliberately skipped so no phantom notes sound.
+    pub fn seek_to(&mut self, seconds: f64, sink: &mut impl MidiSink) -> Result<(), MidiError> {
+        sink.all_sound_off()?;
         let loop_point_in_ticks = (seconds * 1_000_000.0 / self.microseconds_per_tick) as u32;
 
+        let mut channel_state = vec![ChannelChaseState::default(); 16];
+        // The tick position of the latest tempo seen so far, so that tempo
+        // events from different tracks can be compared on the same timeline.
+        let mut latest_tempo: Option<(u32, u24)> = None;
+
         for track_id in 0..self.tracks.len() {
             let mut cumulative_delta = 0;
 
@@ -222,6 +510,25 @@ impl MidiFile {
                 }
 
                 cumulative_delta += event.delta.as_int();
+
+                match &event.kind {
+                    OwnedTrackEventKind::ToSynth(bytes) => {
+                        if let Ok(LiveEvent::Midi { channel, message }) = LiveEvent::parse(bytes) {
+                            channel_state[channel.as_int() as usize].record(message);
+                        }
+                    }
+
+                    OwnedTrackEventKind::Tempo(tempo) => {
+                        if latest_tempo.is_none_or(|(tick, _)| cumulative_delta >= tick) {
+                            latest_tempo = Some((cumulative_delta, *tempo));
+                        }
+                    }
+
+                    // Every other meta event describes a past point in the
+                    // song (a marker, a lyric, ...); there's nothing to chase
+                    // since it has no bearing on the synth's current state.
+                    OwnedTrackEventKind::Meta(_) | OwnedTrackEventKind::InessentialMeta => {}
+                }
             }
 
             // `cumulative_delta` is the time needed to get to the event before
@@ -229,9 +536,28 @@ impl MidiFile {
             self.progress[track_id].ticks_since_last_update =
                 loop_point_in_ticks.saturating_sub(cumulative_delta);
         }
+
+        if let Some((_, tempo)) = latest_tempo {
+            if self.metrical_timing {
+                self.microseconds_per_tick = u32::from(tempo) as f64 / self.ticks_per_beat as f64;
+            }
+        }
+
+        self.elapsed_seconds = seconds;
+
+        for (channel, state) in channel_state.iter().enumerate() {
+            state.replay(u4::new(channel as u8), sink)?;
+        }
+
+        Ok(())
     }
 
-    fn update_track(&mut self, track_id: usize, connection: &mut MidiOutputConnection) {
+    fn update_track(
+        &mut self,
+        track_id: usize,
+        sink: &mut impl MidiSink,
+        on_meta: &mut dyn FnMut(MetaEvent, f64),
+    ) -> Result<(), MidiError> {
         let track = &self.tracks[track_id];
         let progress = &mut self.progress[track_id];
         progress.ticks_since_last_update += 1;
@@ -249,12 +575,21 @@ impl MidiFile {
 
             match &event.kind {
                 OwnedTrackEventKind::ToSynth(event_bytes) => {
-                    connection.send(event_bytes).unwrap();
+                    sink.send(event_bytes)?;
                 }
 
                 OwnedTrackEventKind::Tempo(tempo) => {
-                    self.microseconds_per_tick =
-                        u32::from(*tempo) as f64 / self.ticks_per_beat as f64;
+                    // Tempo meta events only mean anything for metrical
+                    // timing; under timecode timing the tick duration is
+                    // fixed by the frame rate instead.
+                    if self.metrical_timing {
+                        self.microseconds_per_tick =
+                            u32::from(*tempo) as f64 / self.ticks_per_beat as f64;
+                    }
+                }
+
+                OwnedTrackEventKind::Meta(meta_event) => {
+                    on_meta(meta_event.clone(), self.elapsed_seconds)
                 }
 
                 OwnedTrackEventKind::InessentialMeta => {}
@@ -263,22 +598,34 @@ impl MidiFile {
 
         if self.at_end_of_track() {
             if let Some(loop_point) = self.loop_point {
-                self.seek_to(loop_point, connection);
+                self.seek_to(loop_point, sink)?;
             }
         }
+
+        Ok(())
     }
 
-    pub fn update(&mut self, delta_time: f64, connection: &mut MidiOutputConnection) {
+    pub fn update(
+        &mut self,
+        delta_time: f64,
+        sink: &mut impl MidiSink,
+        on_meta: &mut dyn FnMut(MetaEvent, f64),
+    ) -> Result<(), MidiError> {
         if self.paused || self.is_finished() {
-            return;
+            return Ok(());
         }
 
         self.timer += delta_time;
 
-        while self.timer > self.microseconds_per_tick {
+        while self.timer > self.microseconds_per_tick / self.playback_speed {
+            // The song's own position advances one tick's worth of
+            // song-time per iteration, regardless of `playback_speed`: that
+            // only changes how much wall-clock time maps to a tick.
+            self.elapsed_seconds += self.microseconds_per_tick / 1_000_000.0;
+
             match self.format {
                 MidiFileFormat::Sequential { current } => {
-                    self.update_track(current, connection);
+                    self.update_track(current, sink, on_meta)?;
 
                     if self.tracks[current].is_empty() {
                         // This track is finished; play the next track.
@@ -297,43 +644,128 @@ impl MidiFile {
 
                 MidiFileFormat::Parallel => {
                     for track_id in 0..self.tracks.len() {
-                        self.update_track(track_id, connection);
+                        self.update_track(track_id, sink, on_meta)?;
                     }
                 }
             }
 
-            self.timer -= self.microseconds_per_tick;
+            self.timer -= self.microseconds_per_tick / self.playback_speed;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "synth")]
+impl MidiFile {
+    /// Renders the whole file to interleaved stereo `f32` samples at
+    /// `sample_rate` (which must match `sink`'s own sample rate) as fast as
+    /// possible, rather than in real time. The loop point, if any, is
+    /// ignored for the duration of the render so it terminates instead of
+    /// looping forever; it's restored once rendering finishes.
+    pub fn render_to_samples(&mut self, sink: &mut synth::SynthSink, sample_rate: u32) -> Vec<f32> {
+        let saved_loop_point = self.loop_point.take();
+        let micros_per_block =
+            1_000_000.0 * synth::SynthSink::BLOCK_LEN as f64 / sample_rate as f64;
+
+        let mut samples = Vec::new();
+        while !self.at_end_of_track() {
+            self.update(micros_per_block, sink, &mut |_, _| {})
+                .expect("SynthSink::send is infallible");
+            samples.extend(sink.render());
         }
+
+        self.loop_point = saved_loop_point;
+        samples
     }
 }
 
-pub struct MidiPlayer {
+/// Plays [`MidiFile`]s out through a [`MidiSink`], defaulting to the
+/// OS/external synth reached via [`MidiOutputConnection`]. Swap `S` for
+/// something like [`synth::SynthSink`] to drive a software synth instead.
+pub struct MidiPlayer<S: MidiSink = MidiOutputConnection> {
     maybe_midi_file: Option<MidiFile>,
-    connection: MidiOutputConnection,
+    // Already-parsed files queued up behind the current one. Keeping them
+    // pre-parsed (rather than raw bytes) is what makes advancing gapless:
+    // the expensive `Smf::parse` + `OwnedTrackEvent` collection happened
+    // whenever the caller enqueued the file, so advancing is just a pointer
+    // move plus a single All Sound Off.
+    queue: VecDeque<MidiFile>,
+    sink: S,
     last_update_time: Instant,
+    on_meta: Option<Box<dyn FnMut(MetaEvent, f64)>>,
+    on_track_changed: Option<Box<dyn FnMut()>>,
 }
 
-impl MidiPlayer {
-    pub fn new(connection: MidiOutputConnection) -> Self {
+impl<S: MidiSink> MidiPlayer<S> {
+    pub fn new(sink: S) -> Self {
         Self {
             maybe_midi_file: None,
-            connection,
+            queue: VecDeque::new(),
+            sink,
             last_update_time: Instant::now(),
+            on_meta: None,
+            on_track_changed: None,
         }
     }
 
-    pub fn set_midi_file(&mut self, midi_file: MidiFile) {
-        all_sound_off(&mut self.connection);
+    /// Immediately replaces the current file, discarding anything queued.
+    pub fn set_midi_file(&mut self, midi_file: MidiFile) -> Result<(), MidiError> {
+        self.sink.all_sound_off()?;
+        self.queue.clear();
         self.maybe_midi_file = Some(midi_file);
+        Ok(())
     }
 
-    pub fn set_paused(&mut self, paused: bool) {
+    /// Queues `midi_file` to play after the current one finishes, or plays
+    /// it immediately if nothing is currently playing.
+    pub fn enqueue(&mut self, midi_file: MidiFile) -> Result<(), MidiError> {
+        if self.maybe_midi_file.is_none() {
+            self.set_midi_file(midi_file)
+        } else {
+            self.queue.push_back(midi_file);
+            Ok(())
+        }
+    }
+
+    /// Immediately advances to the next queued file, if any.
+    pub fn skip(&mut self) -> Result<(), MidiError> {
+        self.advance()
+    }
+
+    /// Registers a callback fired whenever playback advances to a new file,
+    /// whether via [`Self::skip`] or auto-advancing off the queue.
+    pub fn on_track_changed(&mut self, callback: impl FnMut() + 'static) {
+        self.on_track_changed = Some(Box::new(callback));
+    }
+
+    fn advance(&mut self) -> Result<(), MidiError> {
+        self.sink.all_sound_off()?;
+        self.maybe_midi_file = self.queue.pop_front();
+
+        if let Some(callback) = &mut self.on_track_changed {
+            callback();
+        }
+
+        Ok(())
+    }
+
+    /// Registers a callback fired with every meta event (track name,
+    /// markers, lyrics, key/time signature, ...) and its playback time in
+    /// seconds as playback reaches it, e.g. to drive a karaoke display or
+    /// show song structure in a GUI.
+    pub fn on_meta(&mut self, callback: impl FnMut(MetaEvent, f64) + 'static) {
+        self.on_meta = Some(Box::new(callback));
+    }
+
+    pub fn set_paused(&mut self, paused: bool) -> Result<(), MidiError> {
         if let Some(midi_file) = &mut self.maybe_midi_file {
-            midi_file.set_paused(paused, &mut self.connection);
+            midi_file.set_paused(paused, &mut self.sink)?;
         }
 
         // Don't suddenly jump ahead when unpausing.
         self.last_update_time = Instant::now();
+        Ok(())
     }
 
     pub fn is_finished(&self) -> bool {
@@ -351,20 +783,395 @@ impl MidiPlayer {
     }
 
     /// Seek to the given time in seconds.
-    pub fn seek_to(&mut self, seconds: f64) {
+    pub fn seek_to(&mut self, seconds: f64) -> Result<(), MidiError> {
         if let Some(midi_file) = &mut self.maybe_midi_file {
-            midi_file.seek_to(seconds, &mut self.connection);
+            midi_file.seek_to(seconds, &mut self.sink)?;
         }
+
+        Ok(())
     }
 
-    pub fn update(&mut self) {
+    /// Sets the current file's playback rate (1.0 = normal, 2.0 = double
+    /// speed, 0.5 = half), independent of its authored tempo. Also known as
+    /// tempo scaling.
+    pub fn set_playback_speed(&mut self, playback_speed: f64) {
+        if let Some(midi_file) = &mut self.maybe_midi_file {
+            midi_file.set_playback_speed(playback_speed);
+        }
+    }
+
+    pub fn update(&mut self) -> Result<(), MidiError> {
         let now = Instant::now();
         let delta_time = now.duration_since(self.last_update_time).as_micros() as f64;
 
         if let Some(midi_file) = &mut self.maybe_midi_file {
-            midi_file.update(delta_time, &mut self.connection);
+            let on_meta = &mut self.on_meta;
+            midi_file.update(delta_time, &mut self.sink, &mut |event, time| {
+                if let Some(callback) = on_meta {
+                    callback(event, time);
+                }
+            })?;
+
+            if midi_file.is_finished() {
+                self.advance()?;
+            }
         }
 
         self.last_update_time = now;
+        Ok(())
+    }
+}
+
+/// A Standard MIDI File assembled by [`MidiRecorder::stop`], ready to be
+/// written out or handed straight to [`MidiFile::from_bytes`].
+pub struct RecordedMidi {
+    bytes: Vec<u8>,
+}
+
+impl RecordedMidi {
+    pub fn to_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn write_to(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, &self.bytes)
+    }
+}
+
+/// Records a live MIDI input into a [`RecordedMidi`], the counterpart to
+/// [`MidiPlayer`] playing one back out.
+pub struct MidiRecorder {
+    connection: MidiInputConnection<()>,
+    events: Arc<Mutex<Vec<(Instant, Vec<u8>)>>>,
+    start_time: Instant,
+    ticks_per_beat: u16,
+    tempo: u24,
+}
+
+impl MidiRecorder {
+    /// Starts recording from `port`. Incoming events are timestamped against
+    /// an `Instant` the same way [`MidiPlayer::update`] computes `delta_time`;
+    /// `ticks_per_beat` and `tempo` are used to convert those wall-clock
+    /// deltas back into ticks when the recording is [`stop`](Self::stop)ped.
+    pub fn new(
+        midi_input: MidiInput,
+        port: &MidiInputPort,
+        ticks_per_beat: u16,
+        tempo: u24,
+    ) -> Result<Self, midir::ConnectError<MidiInput>> {
+        let events: Arc<Mutex<Vec<(Instant, Vec<u8>)>>> = Arc::new(Mutex::new(Vec::new()));
+        let callback_events = Arc::clone(&events);
+
+        let connection = midi_input.connect(
+            port,
+            "lyrica-record",
+            move |_stamp, message, _| {
+                callback_events
+                    .lock()
+                    .unwrap()
+                    .push((Instant::now(), message.to_vec()));
+            },
+            (),
+        )?;
+
+        Ok(Self {
+            connection,
+            events,
+            start_time: Instant::now(),
+            ticks_per_beat,
+            tempo,
+        })
+    }
+
+    /// Stops recording and assembles the captured events into a
+    /// [`RecordedMidi`]: a format 0, metrically-timed SMF with a single
+    /// tempo meta at t=0 and an End-of-Track meta.
+    pub fn stop(self) -> RecordedMidi {
+        let (_, ()) = self.connection.close();
+        let events = Arc::try_unwrap(self.events)
+            .unwrap_or_else(|shared| Mutex::new(shared.lock().unwrap().clone()))
+            .into_inner()
+            .unwrap();
+
+        let microseconds_per_tick = u32::from(self.tempo) as f64 / self.ticks_per_beat as f64;
+
+        let mut track = vec![TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::Tempo(self.tempo)),
+        }];
+
+        let mut last_time = self.start_time;
+        for (timestamp, bytes) in &events {
+            let elapsed_ticks =
+                (timestamp.duration_since(last_time).as_micros() as f64 / microseconds_per_tick)
+                    .round() as u32;
+            last_time = *timestamp;
+
+            let kind = match LiveEvent::parse(bytes) {
+                Ok(LiveEvent::Midi { channel, message }) => TrackEventKind::Midi { channel, message },
+                Ok(LiveEvent::SysEx(data)) => TrackEventKind::SysEx(data),
+                _ => continue,
+            };
+
+            track.push(TrackEvent {
+                delta: u28::new(elapsed_ticks.min(0x0FFF_FFFF)),
+                kind,
+            });
+        }
+
+        track.push(TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        });
+
+        let smf = Smf {
+            header: Header {
+                format: Format::SingleTrack,
+                timing: Timing::Metrical(u15::new(self.ticks_per_beat)),
+            },
+            tracks: vec![track],
+        };
+
+        let mut bytes = Vec::new();
+        smf.write_std(&mut bytes)
+            .expect("writing to an in-memory buffer should never fail");
+
+        RecordedMidi { bytes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midly::Fps;
+
+    /// A [`MidiSink`] that just records every message it's sent, for
+    /// asserting on what a [`MidiFile`] sends without a real MIDI port.
+    #[derive(Default)]
+    struct RecordingSink {
+        sent: Vec<Vec<u8>>,
+    }
+
+    impl MidiSink for RecordingSink {
+        fn send(&mut self, bytes: &[u8]) -> Result<(), MidiError> {
+            self.sent.push(bytes.to_vec());
+            Ok(())
+        }
+    }
+
+    fn smf_bytes(timing: Timing, track: Vec<TrackEvent<'static>>) -> Vec<u8> {
+        let smf = Smf {
+            header: Header {
+                format: Format::SingleTrack,
+                timing,
+            },
+            tracks: vec![track],
+        };
+
+        let mut bytes = Vec::new();
+        smf.write_std(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn timecode_timing_fixes_tick_duration_up_front() {
+        let track = vec![TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        }];
+        let bytes = smf_bytes(Timing::Timecode(Fps::Fps30, 80), track);
+
+        let midi_file = MidiFile::from_bytes(&bytes).unwrap();
+
+        assert!(!midi_file.metrical_timing);
+        assert_eq!(midi_file.ticks_per_beat, 0);
+        assert!(
+            (midi_file.microseconds_per_tick - 1_000_000.0 / (30.0 * 80.0)).abs() < 1e-9
+        );
+    }
+
+    #[test]
+    fn metrical_timing_leaves_tick_duration_for_the_first_tempo_event() {
+        let track = vec![TrackEvent {
+            delta: u28::new(0),
+            kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+        }];
+        let bytes = smf_bytes(Timing::Metrical(u15::new(480)), track);
+
+        let midi_file = MidiFile::from_bytes(&bytes).unwrap();
+
+        assert!(midi_file.metrical_timing);
+        assert_eq!(midi_file.ticks_per_beat, 480);
+        assert_eq!(midi_file.microseconds_per_tick, 0.0);
+    }
+
+    #[test]
+    fn tempo_events_are_ignored_under_timecode_timing() {
+        let track = vec![
+            TrackEvent {
+                delta: u28::new(0),
+                kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(500_000))),
+            },
+            TrackEvent {
+                delta: u28::new(1),
+                kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+            },
+        ];
+        let bytes = smf_bytes(Timing::Timecode(Fps::Fps30, 80), track);
+        let mut midi_file = MidiFile::from_bytes(&bytes).unwrap();
+        let fixed_tick_duration = midi_file.microseconds_per_tick;
+
+        // Twice the tick duration runs exactly one tick, which reaches the
+        // Tempo event at tick 0.
+        let mut sink = RecordingSink::default();
+        midi_file
+            .update(fixed_tick_duration * 2.0, &mut sink, &mut |_, _| {})
+            .unwrap();
+
+        assert_eq!(midi_file.microseconds_per_tick, fixed_tick_duration);
+    }
+
+    #[test]
+    fn seek_to_chases_the_latest_per_channel_state_but_not_notes() {
+        let channel = u4::new(0);
+        let track = vec![
+            TrackEvent {
+                delta: u28::new(0),
+                kind: TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::ProgramChange {
+                        program: u7::new(5),
+                    },
+                },
+            },
+            TrackEvent {
+                delta: u28::new(0),
+                kind: TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::Controller {
+                        controller: u7::new(7),
+                        value: u7::new(100),
+                    },
+                },
+            },
+            // Before the seek target, but a chased Note On would sound a
+            // phantom note, so it must not show up in the replayed state below.
+            TrackEvent {
+                delta: u28::new(2),
+                kind: TrackEventKind::Midi {
+                    channel,
+                    message: MidiMessage::NoteOn {
+                        key: u7::new(60),
+                        vel: u7::new(100),
+                    },
+                },
+            },
+            TrackEvent {
+                delta: u28::new(10),
+                kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+            },
+        ];
+        let bytes = smf_bytes(Timing::Metrical(u15::new(480)), track);
+        let mut midi_file = MidiFile::from_bytes(&bytes).unwrap();
+        // Picked so that 0.005s below (tick 5) lands after the Note On
+        // (tick 2) and before the End of Track (tick 12), independent of any
+        // Tempo meta event.
+        midi_file.microseconds_per_tick = 1_000.0;
+
+        let mut sink = RecordingSink::default();
+        midi_file.seek_to(0.005, &mut sink).unwrap();
+
+        // The first 16 messages are `seek_to`'s All Sound Off sweep; what
+        // follows is the chased state itself, in record order.
+        let chased = &sink.sent[16..];
+        assert_eq!(chased.len(), 2);
+
+        match LiveEvent::parse(&chased[0]).unwrap() {
+            LiveEvent::Midi {
+                channel: sent_channel,
+                message: MidiMessage::ProgramChange { program },
+            } => {
+                assert_eq!(sent_channel, channel);
+                assert_eq!(program, u7::new(5));
+            }
+            other => panic!("expected a chased Program Change, got {other:?}"),
+        }
+
+        match LiveEvent::parse(&chased[1]).unwrap() {
+            LiveEvent::Midi {
+                channel: sent_channel,
+                message: MidiMessage::Controller { controller, value },
+            } => {
+                assert_eq!(sent_channel, channel);
+                assert_eq!(controller, u7::new(7));
+                assert_eq!(value, u7::new(100));
+            }
+            other => panic!("expected a chased Controller, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn from_bytes_reports_malformed_input_instead_of_panicking() {
+        let err = MidiFile::from_bytes(b"this is not a Standard MIDI File").unwrap_err();
+        assert!(matches!(err, MidiError::Parse(_)));
+    }
+
+    #[test]
+    fn escape_events_are_forwarded_verbatim() {
+        // Unlike `SysEx`, an escape event's payload carries no leading
+        // status byte to restore, so it should reach the sink unchanged.
+        let payload: &[u8] = &[0xF8, 0x01, 0x02];
+        let track = vec![
+            TrackEvent {
+                delta: u28::new(0),
+                kind: TrackEventKind::Escape(payload),
+            },
+            TrackEvent {
+                delta: u28::new(0),
+                kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+            },
+        ];
+        let bytes = smf_bytes(Timing::Metrical(u15::new(480)), track);
+        let mut midi_file = MidiFile::from_bytes(&bytes).unwrap();
+
+        let mut sink = RecordingSink::default();
+        midi_file.update(1.0, &mut sink, &mut |_, _| {}).unwrap();
+
+        assert_eq!(sink.sent, vec![payload.to_vec()]);
+    }
+
+    #[test]
+    fn time_signature_denominator_saturates_instead_of_overflowing_the_shift() {
+        // The Tempo event gives the first tick a nonzero duration, so
+        // `update` stops after it, before the End of Track at tick 1.
+        let track = vec![
+            TrackEvent {
+                delta: u28::new(0),
+                kind: TrackEventKind::Meta(MetaMessage::Tempo(u24::new(500_000))),
+            },
+            TrackEvent {
+                delta: u28::new(0),
+                kind: TrackEventKind::Meta(MetaMessage::TimeSignature(4, 200, 24, 8)),
+            },
+            TrackEvent {
+                delta: u28::new(1),
+                kind: TrackEventKind::Meta(MetaMessage::EndOfTrack),
+            },
+        ];
+        let bytes = smf_bytes(Timing::Metrical(u15::new(480)), track);
+        let mut midi_file = MidiFile::from_bytes(&bytes).unwrap();
+
+        let mut sink = RecordingSink::default();
+        let mut seen = Vec::new();
+        midi_file
+            .update(1.0, &mut sink, &mut |event, _| seen.push(event))
+            .unwrap();
+
+        match &seen[..] {
+            [MetaEvent::TimeSignature { denominator, .. }] => {
+                assert_eq!(*denominator, u32::MAX);
+            }
+            other => panic!("expected a single TimeSignature meta event, got {other:?}"),
+        }
     }
 }